@@ -4,19 +4,100 @@
 
 #![deny(missing_docs)]
 
+use std::fmt;
+
+/// The reference-counted pointer used to share `Repr` nodes between rope
+/// versions.
+///
+/// By default this is [`std::rc::Rc`], which is not thread-safe. Enable the
+/// `sync` feature to use [`std::sync::Arc`] instead, so a `Rope` can be sent
+/// across threads (at the cost of atomic reference counting).
+#[cfg(not(feature = "sync"))]
+use std::rc::Rc as Shared;
+#[cfg(feature = "sync")]
+use std::sync::Arc as Shared;
+
+/// A user-defined monoidal summary of a rope's contents.
+///
+/// A `Measure` maps each element to a `Summary` and says how to combine two
+/// summaries; the `Rope` caches the combined summary of every `Node` so that
+/// [`Rope::query`] can fold only the O(log n) subtrees on a range's boundary
+/// instead of visiting every element. `combine` must be associative and
+/// `identity` must be its neutral element.
+///
+/// The default measure is `()`, which summarises nothing; give `Rope` a real
+/// `Measure` to use [`Rope::query`].
+///
+/// `Measure` is read-only: there's no lazy tag applied at nodes and pushed
+/// down through `split`, so a bulk range update still has to go through
+/// `remove` + `insert` rather than one O(log n) update to the cached
+/// summaries on a range's boundary. That's tracked separately as
+/// azdavis/nawa#chunk0-6 rather than bolted on here, because `get` and
+/// `iter_range` hand out `&T` straight into a `Repr::Leaf`'s `Vec<T>`, and a
+/// tag sitting unapplied at an ancestor `Node` can't be reconciled with
+/// those borrows without either changing those methods to return owned
+/// values or adding interior mutability to cache the push-down — both
+/// bigger calls than this request.
+pub trait Measure<T> {
+  /// The summary of a (sub)sequence of elements.
+  type Summary: Clone;
+
+  /// The summary of a single element.
+  fn measure(x: &T) -> Self::Summary;
+
+  /// Combines the summaries of two adjacent sequences. Must be associative.
+  fn combine(a: &Self::Summary, b: &Self::Summary) -> Self::Summary;
+
+  /// The summary of the empty sequence, i.e. the identity of `combine`.
+  fn identity() -> Self::Summary;
+}
+
+/// The trivial measure, which summarises nothing. This is the default, so a
+/// plain `Rope<T>` carries no per-node summary overhead.
+impl<T> Measure<T> for () {
+  type Summary = ();
+
+  fn measure(_: &T) {}
+
+  fn combine(_: &(), _: &()) {}
+
+  fn identity() {}
+}
+
 /// A rope data structure.
-#[derive(Debug, Clone)]
-pub struct Rope<T> {
-  repr: Repr<T>,
+///
+/// The tree lives behind [`Shared`] pointers, so [`Clone`] is O(1) regardless
+/// of `T` and prior versions stay cheaply available: `insert`/`remove` rebuild
+/// only the O(log n) nodes along the edited path, sharing every untouched
+/// subtree with the original. Rotations and rebalancing share subtrees through
+/// reference-count bumps and never touch `T`.
+///
+/// `insert`/`remove` require `T: Clone`: because a leaf is a `Vec<T>`, the
+/// leaf straddling a cut is duplicated into its two halves, and two adjacent
+/// leaves whose combined size fits in one leaf are merged by copying their
+/// elements. Other leaves are shared in O(1). Read access (`get`, `iter`,
+/// `query`, `len`, …) never needs `T: Clone`.
+///
+/// The optional second parameter `M` is a [`Measure`] whose summary is cached
+/// in each node for [`Rope::query`]; it defaults to `()` (no summary).
+pub struct Rope<T, M: Measure<T> = ()> {
+  repr: Shared<Repr<T, M>>,
 }
 
-impl<T> Rope<T> {
+impl<T, M: Measure<T>> Rope<T, M> {
+  /// The maximum number of elements stored in a single leaf.
+  ///
+  /// `From`/`insert` chop larger payloads into a balanced fringe of leaves
+  /// no bigger than this; tune it for your element type by reading it when
+  /// sizing the `Vec`s you insert.
+  pub const CHUNK_SIZE: usize = MAX_LEAF;
+
   /// Returns an empty `Rope`.
   ///
   /// Computes in O(1).
   #[allow(clippy::new_without_default)]
   pub fn new() -> Self {
-    Self::of(Repr::new())
+    Self::of(Shared::new(Repr::new()))
   }
 
   /// Returns the length of this `Rope`.
@@ -30,7 +111,7 @@ impl<T> Rope<T> {
   ///
   /// let r: Rope<i32> = Rope::new();
   /// assert_eq!(r.len(), 0);
-  /// let r = Rope::from(vec![2, 4, 6]);
+  /// let r: Rope<i32> = Rope::from(vec![2, 4, 6]);
   /// assert_eq!(r.len(), 3);
   /// ```
   pub fn len(&self) -> usize {
@@ -48,7 +129,7 @@ impl<T> Rope<T> {
   ///
   /// let r: Rope<i32> = Rope::new();
   /// assert!(r.is_empty());
-  /// let r = Rope::from(vec![2, 4, 6]);
+  /// let r: Rope<i32> = Rope::from(vec![2, 4, 6]);
   /// assert!(!r.is_empty());
   /// ```
   pub fn is_empty(&self) -> bool {
@@ -64,14 +145,17 @@ impl<T> Rope<T> {
   /// ```
   /// use nawa::Rope;
   ///
-  /// let r = Rope::from(vec![2, 4]);
+  /// let r: Rope<i32> = Rope::from(vec![2, 4]);
   /// assert_eq!(r.to_vec(), [&2, &4]);
   /// let r = r.insert(1, vec![3, 5]);
   /// assert_eq!(r.to_vec(), [&2, &3, &5, &4]);
   /// ```
-  pub fn insert(self, i: usize, xs: Vec<T>) -> Self {
-    let (a, c) = self.repr.split(i);
-    let b = Repr::Leaf(xs);
+  pub fn insert(&self, i: usize, xs: Vec<T>) -> Self
+  where
+    T: Clone,
+  {
+    let (a, c) = Repr::split(&self.repr, i);
+    let b = Repr::leaf(xs);
     Self::of(Repr::node(a, Repr::node(b, c)))
   }
 
@@ -84,17 +168,119 @@ impl<T> Rope<T> {
   /// ```
   /// use nawa::Rope;
   ///
-  /// let r = Rope::from(vec![2, 4, 6, 8]);
+  /// let r: Rope<i32> = Rope::from(vec![2, 4, 6, 8]);
   /// let r = r.remove(1..3);
   /// assert_eq!(r.to_vec(), [&2, &8]);
   /// ```
-  pub fn remove(self, range: std::ops::Range<usize>) -> Self {
+  pub fn remove(&self, range: std::ops::Range<usize>) -> Self
+  where
+    T: Clone,
+  {
     assert!(range.start <= range.end);
-    let (a, b) = self.repr.split(range.start);
-    let (_, d) = b.split(range.end - range.start);
+    let (a, b) = Repr::split(&self.repr, range.start);
+    let (_, d) = Repr::split(&b, range.end - range.start);
     Self::of(Repr::node(a, d))
   }
 
+  /// Returns a reference to the element at `i`, or `None` if `i >= len`.
+  ///
+  /// Computes in O(log n) by descending the size-annotated tree.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use nawa::Rope;
+  ///
+  /// let r: Rope<i32> = Rope::from(vec![2, 4, 6]);
+  /// assert_eq!(r.get(1), Some(&4));
+  /// assert_eq!(r.get(3), None);
+  /// ```
+  pub fn get(&self, i: usize) -> Option<&T> {
+    if i < self.len() {
+      Some(self.repr.get(i))
+    } else {
+      None
+    }
+  }
+
+  /// Returns an iterator over the elements in `range`, in order.
+  ///
+  /// The iterator descends to `range.start` in O(log n) and yields only the
+  /// requested subrange, without materializing the rest of the rope.
+  ///
+  /// Panics iff the range is out of bounds.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use nawa::Rope;
+  ///
+  /// let r: Rope<i32> = Rope::from(vec![2, 4, 6, 8]);
+  /// let got: Vec<&i32> = r.iter_range(1..3).collect();
+  /// assert_eq!(got, [&4, &6]);
+  /// ```
+  pub fn iter_range(&self, range: std::ops::Range<usize>) -> Iter<'_, T, M> {
+    assert!(range.start <= range.end);
+    assert!(range.end <= self.len());
+    Iter::new(&self.repr, range.start, range.end - range.start)
+  }
+
+  /// Returns an iterator over every element, in order.
+  pub fn iter(&self) -> Iter<'_, T, M> {
+    self.iter_range(0..self.len())
+  }
+
+  /// Returns the combined [`Measure::Summary`] of the elements in `range`.
+  ///
+  /// Computes in O(log n) by folding the cached summaries of the O(log n)
+  /// subtrees on the range's boundary, rather than visiting every element.
+  /// An empty range yields [`Measure::identity`].
+  ///
+  /// Panics iff the range is out of bounds.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use nawa::{Measure, Rope};
+  ///
+  /// struct Sum;
+  ///
+  /// impl Measure<i32> for Sum {
+  ///   type Summary = i32;
+  ///   fn measure(x: &i32) -> i32 {
+  ///     *x
+  ///   }
+  ///   fn combine(a: &i32, b: &i32) -> i32 {
+  ///     a + b
+  ///   }
+  ///   fn identity() -> i32 {
+  ///     0
+  ///   }
+  /// }
+  ///
+  /// let r: Rope<i32, Sum> = Rope::from(vec![1, 2, 3, 4]);
+  /// assert_eq!(r.query(1..3), 5);
+  /// assert_eq!(r.query(0..4), 10);
+  /// assert_eq!(r.query(2..2), 0);
+  /// ```
+  pub fn query(&self, range: std::ops::Range<usize>) -> M::Summary {
+    assert!(range.start <= range.end);
+    assert!(range.end <= self.len());
+    if range.start == range.end {
+      M::identity()
+    } else {
+      self.repr.query(range.start, range.end)
+    }
+  }
+
+  /// Returns the height of the underlying tree.
+  ///
+  /// Exposed chiefly to let tests check that the weight-balance invariant
+  /// keeps the tree at O(log n) depth rather than degenerating into a spine.
+  pub fn depth(&self) -> usize {
+    self.repr.depth()
+  }
+
   /// Returns the `Vec` represented by this `Rope`.
   ///
   /// Computes in O(n).
@@ -104,57 +290,202 @@ impl<T> Rope<T> {
   /// ```
   /// use nawa::Rope;
   ///
-  /// let r = Rope::from(vec![2, 4, 6, 8]);
+  /// let r: Rope<i32> = Rope::from(vec![2, 4, 6, 8]);
   /// assert_eq!(r.to_vec(), [&2, &4, &6, &8]);
   /// ```
   pub fn to_vec(&self) -> Vec<&T> {
-    self.repr.to_vec()
+    self.iter().collect()
   }
 
   #[inline(always)]
-  fn of(repr: Repr<T>) -> Self {
+  fn of(repr: Shared<Repr<T, M>>) -> Self {
     Self { repr }
   }
 }
 
-impl<T: PartialEq> PartialEq for Rope<T> {
-  fn eq(&self, other: &Rope<T>) -> bool {
+impl<T, M: Measure<T>> Clone for Rope<T, M> {
+  fn clone(&self) -> Self {
+    Self::of(Shared::clone(&self.repr))
+  }
+}
+
+impl<T: fmt::Debug, M: Measure<T>> fmt::Debug for Rope<T, M>
+where
+  M::Summary: fmt::Debug,
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Rope").field("repr", &self.repr).finish()
+  }
+}
+
+impl<T: PartialEq, M: Measure<T>> PartialEq for Rope<T, M> {
+  fn eq(&self, other: &Rope<T, M>) -> bool {
     self.to_vec() == other.to_vec()
   }
 }
 
-impl<T: Eq> Eq for Rope<T> {}
+impl<T: Eq, M: Measure<T>> Eq for Rope<T, M> {}
 
-impl<T: PartialOrd> PartialOrd for Rope<T> {
-  fn partial_cmp(&self, other: &Rope<T>) -> Option<std::cmp::Ordering> {
+impl<T: PartialOrd, M: Measure<T>> PartialOrd for Rope<T, M> {
+  fn partial_cmp(&self, other: &Rope<T, M>) -> Option<std::cmp::Ordering> {
     self.to_vec().partial_cmp(&other.to_vec())
   }
 }
 
-impl<T: Ord> Ord for Rope<T> {
+impl<T: Ord, M: Measure<T>> Ord for Rope<T, M> {
   fn cmp(&self, other: &Self) -> std::cmp::Ordering {
     self.to_vec().cmp(&other.to_vec())
   }
 }
 
-impl<T> From<Vec<T>> for Rope<T> {
+impl<T, M: Measure<T>> From<Vec<T>> for Rope<T, M> {
   fn from(val: Vec<T>) -> Self {
-    Self::of(Repr::Leaf(val))
+    Self::of(Repr::leaf(val))
+  }
+}
+
+impl<T, M: Measure<T>> FromIterator<T> for Rope<T, M> {
+  fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+    Self::from(iter.into_iter().collect::<Vec<T>>())
+  }
+}
+
+impl<T: Clone, M: Measure<T>> IntoIterator for Rope<T, M> {
+  type Item = T;
+  type IntoIter = std::vec::IntoIter<T>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter().cloned().collect::<Vec<T>>().into_iter()
   }
 }
 
-enum Direction {
-  Left,
-  Right,
+impl<'a, T, M: Measure<T>> IntoIterator for &'a Rope<T, M> {
+  type Item = &'a T;
+  type IntoIter = Iter<'a, T, M>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
 }
 
-#[derive(Debug, Clone)]
-enum Repr<T> {
+/// A lazy iterator over a subrange of a [`Rope`], yielding references in
+/// order. Created by [`Rope::iter_range`] and [`Rope::iter`].
+pub struct Iter<'a, T, M: Measure<T>> {
+  /// Subtrees still to visit in full, innermost (next to visit) last.
+  stack: Vec<&'a Repr<T, M>>,
+  /// The current leaf, positioned at the next element to yield.
+  leaf: std::slice::Iter<'a, T>,
+  /// How many more elements to yield before stopping.
+  remaining: usize,
+}
+
+impl<'a, T, M: Measure<T>> Iter<'a, T, M> {
+  /// Seeds an iterator at the `lo`th element that will yield `remaining`
+  /// elements. Descends to `lo` in O(log n), pushing the right subtrees that
+  /// must still be visited onto the stack.
+  fn new(root: &'a Repr<T, M>, mut lo: usize, remaining: usize) -> Self {
+    let mut stack = Vec::new();
+    let mut node = root;
+    loop {
+      match node {
+        Repr::Leaf(xs) => {
+          return Iter { stack, leaf: xs[lo..].iter(), remaining };
+        }
+        Repr::Node(l, _, _, r) => {
+          let ln = l.len();
+          if lo < ln {
+            stack.push(r.as_ref());
+            node = l;
+          } else {
+            lo -= ln;
+            node = r;
+          }
+        }
+      }
+    }
+  }
+}
+
+impl<'a, T, M: Measure<T>> Iterator for Iter<'a, T, M> {
+  type Item = &'a T;
+
+  fn next(&mut self) -> Option<&'a T> {
+    if self.remaining == 0 {
+      return None;
+    }
+    loop {
+      if let Some(x) = self.leaf.next() {
+        self.remaining -= 1;
+        return Some(x);
+      }
+      let mut node = self.stack.pop()?;
+      loop {
+        match node {
+          Repr::Leaf(xs) => {
+            self.leaf = xs.iter();
+            break;
+          }
+          Repr::Node(l, _, _, r) => {
+            self.stack.push(r.as_ref());
+            node = l;
+          }
+        }
+      }
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.remaining, Some(self.remaining))
+  }
+}
+
+impl<T, M: Measure<T>> ExactSizeIterator for Iter<'_, T, M> {}
+
+/// Bounds how lopsided `link` lets a `Node` be: when one child's size exceeds
+/// `DELTA` times the other's and that heavier child is a `Node`, it is
+/// rotated. With `DELTA = 3` this is the weight-balanced BB[α] invariant with
+/// α = 1/4 (the larger child is at most 3× the smaller). A heavier child that
+/// is a `Leaf` is not rotated, so such a `Node` can exceed this ratio.
+const DELTA: usize = 3;
+
+/// Once a rotation is needed, decides whether a single rotation suffices
+/// (the offending grandchild is "outer") or a double rotation is required
+/// (it is "inner").
+const RATIO: usize = 2;
+
+/// The maximum number of elements in a single `Leaf`.
+///
+/// Oversized payloads handed to `From`/`insert` are chopped into a balanced
+/// fringe of leaves no larger than this, so a `split` only ever copies within
+/// one chunk and scans stay inside a few cache lines. Two adjacent leaves
+/// whose combined size fits here are merged by `node`, keeping the tree from
+/// degenerating into a skinny spine of tiny leaves.
+const MAX_LEAF: usize = 1024;
+
+type Link<T, M> = Shared<Repr<T, M>>;
+
+enum Repr<T, M: Measure<T>> {
   Leaf(Vec<T>),
-  Node(Box<Repr<T>>, usize, Box<Repr<T>>),
+  Node(Link<T, M>, usize, M::Summary, Link<T, M>),
+}
+
+// `Repr` holds an `M::Summary` in each `Node`, so a derived `Debug` would need
+// a `M::Summary: Debug` bound that `derive` cannot infer. Write it by hand.
+impl<T: fmt::Debug, M: Measure<T>> fmt::Debug for Repr<T, M>
+where
+  M::Summary: fmt::Debug,
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Repr::Leaf(xs) => f.debug_tuple("Leaf").field(xs).finish(),
+      Repr::Node(l, len, s, r) => {
+        f.debug_tuple("Node").field(l).field(len).field(s).field(r).finish()
+      }
+    }
+  }
 }
 
-impl<T> Repr<T> {
+impl<T, M: Measure<T>> Repr<T, M> {
   fn new() -> Self {
     Self::Leaf(Vec::new())
   }
@@ -162,74 +493,251 @@ impl<T> Repr<T> {
   fn len(&self) -> usize {
     match *self {
       Repr::Leaf(ref xs) => xs.len(),
-      Repr::Node(_, len, _) => len,
+      Repr::Node(_, len, _, _) => len,
     }
   }
 
-  fn node(left: Self, right: Self) -> Self {
-    match (left.len(), right.len()) {
-      (0, _) => right,
-      (_, 0) => left,
-      (a, b) => Self::Node(left.into(), a + b, right.into()),
+  fn depth(&self) -> usize {
+    match self {
+      Repr::Leaf(_) => 1,
+      Repr::Node(l, _, _, r) => 1 + l.depth().max(r.depth()),
     }
   }
 
-  fn split(self, mut i: usize) -> (Self, Self) {
-    if i > self.len() {
-      panic!(
-        "index out of bounds: the len is {} but the index is {}",
-        self.len(),
-        i
-      );
+  fn is_node(&self) -> bool {
+    matches!(*self, Repr::Node(..))
+  }
+
+  fn is_leaf(&self) -> bool {
+    matches!(*self, Repr::Leaf(_))
+  }
+
+  /// Returns the summary of this whole subtree, using the cached value at a
+  /// `Node` and folding a `Leaf`'s elements directly.
+  fn summary(&self) -> M::Summary {
+    match self {
+      Repr::Leaf(xs) => xs.iter().fold(M::identity(), |acc, x| {
+        M::combine(&acc, &M::measure(x))
+      }),
+      Repr::Node(_, _, s, _) => s.clone(),
     }
-    let mut work = Vec::with_capacity(1);
-    let mut right = self;
-    let mut left = loop {
-      match right {
-        Repr::Leaf(mut xs) => {
-          right = Repr::Leaf(xs.split_off(i));
-          break Repr::Leaf(xs);
+  }
+
+  /// Returns a reference to the `i`th element. The caller guarantees
+  /// `i < len`.
+  fn get(&self, mut i: usize) -> &T {
+    let mut this = self;
+    loop {
+      match this {
+        Repr::Leaf(xs) => return &xs[i],
+        Repr::Node(l, _, _, r) => {
+          let ln = l.len();
+          if i < ln {
+            this = l;
+          } else {
+            i -= ln;
+            this = r;
+          }
         }
-        Repr::Node(node_l, _, node_r) => {
-          if i < node_l.len() {
-            work.push((*node_r, Direction::Right));
-            right = *node_l;
+      }
+    }
+  }
+
+  /// Returns the summary of `self[lo..hi]`. The caller guarantees
+  /// `lo < hi <= len`. O(log n): a fully covered subtree is answered by its
+  /// cached summary, so only the two boundary paths are descended.
+  fn query(&self, lo: usize, hi: usize) -> M::Summary {
+    match self {
+      Repr::Leaf(xs) => xs[lo..hi].iter().fold(M::identity(), |acc, x| {
+        M::combine(&acc, &M::measure(x))
+      }),
+      Repr::Node(l, len, s, r) => {
+        if lo == 0 && hi == *len {
+          s.clone()
+        } else {
+          let ln = l.len();
+          if hi <= ln {
+            l.query(lo, hi)
+          } else if lo >= ln {
+            r.query(lo - ln, hi - ln)
           } else {
-            i -= node_l.len();
-            work.push((*node_l, Direction::Left));
-            right = *node_r;
+            M::combine(&l.query(lo, ln), &r.query(0, hi - ln))
           }
         }
       }
-    };
-    for (repr, dir) in work.into_iter().rev() {
-      match dir {
-        Direction::Left => left = Repr::node(repr, left),
-        Direction::Right => right = Repr::node(right, repr),
+    }
+  }
+
+  /// The two children of a `Node`, each shared in O(1). Callers only invoke
+  /// this on a side they have already checked with `is_node`.
+  fn children(n: &Link<T, M>) -> (Link<T, M>, Link<T, M>) {
+    match &**n {
+      Repr::Node(l, _, _, r) => (Shared::clone(l), Shared::clone(r)),
+      Repr::Leaf(_) => unreachable!("children called on a leaf"),
+    }
+  }
+
+  /// Builds a `Node` from two non-empty subtrees, caching their combined
+  /// size and summary. Shares both children in O(1); performs no rebalancing.
+  fn bin(left: Link<T, M>, right: Link<T, M>) -> Link<T, M> {
+    let len = left.len() + right.len();
+    let summary = M::combine(&left.summary(), &right.summary());
+    Shared::new(Repr::Node(left, len, summary, right))
+  }
+
+  /// Concatenates two subtrees into a balanced one.
+  ///
+  /// When the combined shape would violate the weight-balance invariant this
+  /// descends recursively into the heavy side — reattaching through `link`
+  /// itself, not a raw `bin` — until the subtrees being joined are within
+  /// `DELTA`, then applies a single or double rotation. This corrects the
+  /// large, lopsided merges `node` actually sees (a small leaf joined against
+  /// a whole existing subtree), keeping depth O(log n).
+  fn link(left: Link<T, M>, right: Link<T, M>) -> Link<T, M> {
+    let a = left.len();
+    let b = right.len();
+    if a == 0 {
+      right
+    } else if b == 0 {
+      left
+    } else if b > DELTA * a && right.is_node() {
+      let (rl, rr) = Self::children(&right);
+      Self::balance(Self::link(left, rl), rr)
+    } else if a > DELTA * b && left.is_node() {
+      let (ll, lr) = Self::children(&left);
+      Self::balance(ll, Self::link(lr, right))
+    } else {
+      Self::bin(left, right)
+    }
+  }
+
+  /// Applies a single or double rotation to restore the weight-balance
+  /// invariant at one level, given children that are off by at most the bound
+  /// `link`'s recursion leaves behind. Reattaches through `bin` (the regrouped
+  /// grandchildren are already balanced).
+  fn balance(left: Link<T, M>, right: Link<T, M>) -> Link<T, M> {
+    let a = left.len();
+    let b = right.len();
+    if b > DELTA * a && right.is_node() {
+      let (rl, rr) = Self::children(&right);
+      if rl.len() < RATIO * rr.len() || !rl.is_node() {
+        Self::bin(Self::bin(left, rl), rr)
+      } else {
+        let (rll, rlr) = Self::children(&rl);
+        Self::bin(Self::bin(left, rll), Self::bin(rlr, rr))
+      }
+    } else if a > DELTA * b && left.is_node() {
+      let (ll, lr) = Self::children(&left);
+      if lr.len() < RATIO * ll.len() || !lr.is_node() {
+        Self::bin(ll, Self::bin(lr, right))
+      } else {
+        let (lrl, lrr) = Self::children(&lr);
+        Self::bin(Self::bin(ll, lrl), Self::bin(lrr, right))
       }
+    } else {
+      Self::bin(left, right)
     }
-    (left, right)
   }
 
-  fn to_vec(&self) -> Vec<&T> {
-    let mut ret = Vec::with_capacity(self.len());
-    let mut work = Vec::with_capacity(1);
-    let mut this = self;
-    loop {
-      match this {
-        Repr::Leaf(xs) => {
-          ret.extend(xs.iter());
-          match work.pop() {
-            None => break,
-            Some(right) => this = right,
-          }
+  /// Concatenates two subtrees, first folding two adjacent under-full leaves
+  /// together (minimum-fill) rather than stacking them into a skinny spine.
+  fn node(left: Link<T, M>, right: Link<T, M>) -> Link<T, M>
+  where
+    T: Clone,
+  {
+    let (a, b) = (left.len(), right.len());
+    if a != 0 && b != 0 && a + b <= MAX_LEAF && left.is_leaf() && right.is_leaf()
+    {
+      Self::merge_leaves(&left, &right)
+    } else {
+      Self::link(left, right)
+    }
+  }
+
+  /// Concatenates two leaves into one. Callers check both sides are leaves.
+  fn merge_leaves(left: &Self, right: &Self) -> Link<T, M>
+  where
+    T: Clone,
+  {
+    match (left, right) {
+      (Repr::Leaf(l), Repr::Leaf(r)) => {
+        let mut xs = Vec::with_capacity(l.len() + r.len());
+        xs.extend(l.iter().cloned());
+        xs.extend(r.iter().cloned());
+        Shared::new(Repr::Leaf(xs))
+      }
+      _ => unreachable!("merge_leaves called on a non-leaf"),
+    }
+  }
+
+  /// Builds a (possibly chunked) subtree from a flat `Vec`. A payload over
+  /// `MAX_LEAF` is chopped into bounded leaves joined into a balanced fringe.
+  fn leaf(mut xs: Vec<T>) -> Link<T, M> {
+    if xs.len() <= MAX_LEAF {
+      return Shared::new(Repr::Leaf(xs));
+    }
+    let mut leaves = Vec::with_capacity(xs.len() / MAX_LEAF + 1);
+    while xs.len() > MAX_LEAF {
+      let rest = xs.split_off(MAX_LEAF);
+      leaves.push(Shared::new(Repr::Leaf(xs)));
+      xs = rest;
+    }
+    leaves.push(Shared::new(Repr::Leaf(xs)));
+    Self::from_leaves(leaves)
+  }
+
+  /// Joins a list of leaves into a balanced tree by combining adjacent pairs
+  /// level by level. Each leaf is non-empty, so no rebalancing is needed.
+  fn from_leaves(mut level: Vec<Link<T, M>>) -> Link<T, M> {
+    if level.is_empty() {
+      return Shared::new(Repr::new());
+    }
+    while level.len() > 1 {
+      let mut next = Vec::with_capacity(level.len().div_ceil(2));
+      let mut it = level.into_iter();
+      while let Some(a) = it.next() {
+        match it.next() {
+          Some(b) => next.push(Self::bin(a, b)),
+          None => next.push(a),
         }
-        Repr::Node(left, _, right) => {
-          work.push(right);
-          this = left;
+      }
+      level = next;
+    }
+    level.into_iter().next().unwrap()
+  }
+
+  /// Splits the subtree at `i`, returning the elements before and from `i`.
+  ///
+  /// Shares all untouched subtrees with the input in O(1); only the leaf
+  /// straddling `i`, and any small leaves `node` merges while rejoining, have
+  /// their elements copied (hence `T: Clone`), and only the O(log n) nodes on
+  /// the path to it are rebuilt.
+  fn split(this: &Link<T, M>, i: usize) -> (Link<T, M>, Link<T, M>)
+  where
+    T: Clone,
+  {
+    assert!(
+      i <= this.len(),
+      "index out of bounds: the len is {} but the index is {}",
+      this.len(),
+      i
+    );
+    match &**this {
+      Repr::Leaf(xs) => {
+        let left = Shared::new(Repr::Leaf(xs[..i].to_vec()));
+        let right = Shared::new(Repr::Leaf(xs[i..].to_vec()));
+        (left, right)
+      }
+      Repr::Node(l, _, _, r) => {
+        let ln = l.len();
+        if i < ln {
+          let (a, b) = Self::split(l, i);
+          (a, Self::node(b, Shared::clone(r)))
+        } else {
+          let (a, b) = Self::split(r, i - ln);
+          (Self::node(Shared::clone(l), a), b)
         }
       }
     }
-    ret
   }
 }
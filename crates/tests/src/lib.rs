@@ -50,5 +50,176 @@ fn random() {
     assert_eq!(naive.len(), nawa.len());
     assert_eq!(naive.is_empty(), nawa.is_empty());
     assert_eq!(naive.to_vec(), nawa.to_vec());
+    if nawa.len() > 0 {
+      let leaves = nawa.len().div_ceil(nawa::Rope::<u32>::CHUNK_SIZE).max(1);
+      let bound = 4 * (leaves as f64).log2().ceil() as usize + 8;
+      assert!(
+        nawa.depth() <= bound,
+        "depth {} exceeded bound {} at len {}",
+        nawa.depth(),
+        bound,
+        nawa.len()
+      );
+    }
+  }
+}
+
+#[test]
+fn chunking_and_minimum_fill() {
+  let chunk = nawa::Rope::<u32>::CHUNK_SIZE;
+
+  // Exactly `CHUNK_SIZE` elements fit in a single leaf: no `Node` layer.
+  let exact: nawa::Rope<u32> =
+    nawa::Rope::from((0..chunk as u32).collect::<Vec<_>>());
+  assert_eq!(exact.depth(), 1);
+  assert_eq!(exact.len(), chunk);
+
+  // One element over `CHUNK_SIZE` is chopped into a fringe of leaves, each
+  // no bigger than `CHUNK_SIZE`, joined under a `Node`.
+  let over: nawa::Rope<u32> =
+    nawa::Rope::from((0..(chunk as u32 + 1)).collect::<Vec<_>>());
+  assert!(
+    over.depth() > 1,
+    "payload over CHUNK_SIZE should be split across leaves"
+  );
+  assert_eq!(over.to_vec().len(), chunk + 1);
+
+  // Two small adjacent leaves, joined via `insert`'s call to `node`, are
+  // merged into one leaf by the minimum-fill rule rather than left as a
+  // two-leaf spine.
+  let small: nawa::Rope<u32> = nawa::Rope::new();
+  let small = small.insert(0, vec![1, 2, 3]);
+  let small = small.insert(3, vec![4, 5, 6]);
+  assert_eq!(
+    small.depth(),
+    1,
+    "two small adjacent leaves should merge into one"
+  );
+  assert_eq!(small.to_vec(), [&1, &2, &3, &4, &5, &6]);
+}
+
+struct Sum;
+
+impl nawa::Measure<u32> for Sum {
+  type Summary = u64;
+
+  fn measure(x: &u32) -> u64 {
+    u64::from(*x)
+  }
+
+  fn combine(a: &u64, b: &u64) -> u64 {
+    a + b
+  }
+
+  fn identity() -> u64 {
+    0
+  }
+}
+
+#[test]
+fn query_on_chunked_rebalanced_tree() {
+  let seed = get_seed();
+  println!("seed: {}", seed);
+  let mut rand = Rand32::new(seed);
+
+  // Seed well past a single `CHUNK_SIZE` leaf so this starts out chopped
+  // into a fringe of bounded leaves, then keep it there across inserts and
+  // removes so `query`'s boundary-splitting logic gets exercised against
+  // nodes that have actually been rotated, not just a single untouched leaf.
+  let chunk = nawa::Rope::<u32>::CHUNK_SIZE as u32;
+  let initial: Vec<u32> = (0..(4 * chunk)).collect();
+  let mut xs = initial.clone();
+  let mut rope: nawa::Rope<u32, Sum> = nawa::Rope::from(initial);
+
+  for _ in 0..5000 {
+    match rand.rand_range(0..2) {
+      0 => {
+        let n = rand_usize(&mut rand, 1..50);
+        let new: Vec<u32> =
+          (0..n).map(|_| rand.rand_range(0..1000)).collect();
+        let i = rand_usize(&mut rand, 0..(xs.len() + 1));
+        rope = rope.insert(i, new.clone());
+        xs.splice(i..i, new);
+      }
+      1 => {
+        if xs.is_empty() {
+          continue;
+        }
+        // Remove at most 25 elements, fewer than an insert adds on average,
+        // so the rope grows rather than shrinking below `CHUNK_SIZE`.
+        let start = rand_usize(&mut rand, 0..xs.len());
+        let end =
+          rand_usize(&mut rand, (start + 1)..(xs.len().min(start + 25) + 1));
+        rope = rope.remove(start..end);
+        xs.drain(start..end);
+      }
+      _ => unreachable!(),
+    }
+  }
+
+  assert!(xs.len() > nawa::Rope::<u32>::CHUNK_SIZE, "should stay chunked");
+
+  for _ in 0..500 {
+    if xs.is_empty() {
+      break;
+    }
+    let start = rand_usize(&mut rand, 0..xs.len());
+    let end = rand_usize(&mut rand, start..(xs.len() + 1));
+    let want: u64 = xs[start..end].iter().map(|&x| u64::from(x)).sum();
+    assert_eq!(rope.query(start..end), want, "range {}..{}", start, end);
+  }
+}
+
+#[test]
+fn iter_range_on_chunked_rebalanced_tree() {
+  let seed = get_seed();
+  println!("seed: {}", seed);
+  let mut rand = Rand32::new(seed);
+
+  // Same churn as `query_on_chunked_rebalanced_tree`: seed past a single
+  // `CHUNK_SIZE` leaf and keep inserting/removing so `iter_range` has to
+  // descend into a tree that's actually been chunked and rotated, not just
+  // resume at `lo == 0` the way `to_vec`/`iter` always do.
+  let chunk = nawa::Rope::<u32>::CHUNK_SIZE as u32;
+  let initial: Vec<u32> = (0..(4 * chunk)).collect();
+  let mut xs = initial.clone();
+  let mut rope: nawa::Rope<u32> = nawa::Rope::from(initial);
+
+  for _ in 0..5000 {
+    match rand.rand_range(0..2) {
+      0 => {
+        let n = rand_usize(&mut rand, 1..50);
+        let new: Vec<u32> =
+          (0..n).map(|_| rand.rand_range(0..1000)).collect();
+        let i = rand_usize(&mut rand, 0..(xs.len() + 1));
+        rope = rope.insert(i, new.clone());
+        xs.splice(i..i, new);
+      }
+      1 => {
+        if xs.is_empty() {
+          continue;
+        }
+        // Remove at most 25 elements, as in `query_on_chunked_rebalanced_tree`.
+        let start = rand_usize(&mut rand, 0..xs.len());
+        let end =
+          rand_usize(&mut rand, (start + 1)..(xs.len().min(start + 25) + 1));
+        rope = rope.remove(start..end);
+        xs.drain(start..end);
+      }
+      _ => unreachable!(),
+    }
+  }
+
+  assert!(xs.len() > nawa::Rope::<u32>::CHUNK_SIZE, "should stay chunked");
+
+  for _ in 0..500 {
+    if xs.is_empty() {
+      break;
+    }
+    let start = rand_usize(&mut rand, 0..xs.len());
+    let end = rand_usize(&mut rand, start..(xs.len() + 1));
+    let want: Vec<&u32> = xs[start..end].iter().collect();
+    let got: Vec<&u32> = rope.iter_range(start..end).collect();
+    assert_eq!(got, want, "range {}..{}", start, end);
   }
 }
@@ -1,6 +1,6 @@
 //! A trait for ropes.
 
-pub trait Rope<T> {
+pub trait Rope<T: Clone> {
   fn new() -> Self;
   fn len(&self) -> usize;
   fn is_empty(&self) -> bool;
@@ -9,7 +9,7 @@ pub trait Rope<T> {
   fn to_vec(&self) -> Vec<&T>;
 }
 
-impl<T> Rope<T> for nawa::Rope<T> {
+impl<T: Clone> Rope<T> for nawa::Rope<T> {
   fn new() -> Self {
     Self::new()
   }
@@ -23,11 +23,11 @@ impl<T> Rope<T> for nawa::Rope<T> {
   }
 
   fn insert(self, i: usize, xs: Vec<T>) -> Self {
-    self.insert(i, xs)
+    nawa::Rope::insert(&self, i, xs)
   }
 
   fn remove(self, range: std::ops::Range<usize>) -> Self {
-    self.remove(range)
+    nawa::Rope::remove(&self, range)
   }
 
   fn to_vec(&self) -> Vec<&T> {
@@ -35,7 +35,7 @@ impl<T> Rope<T> for nawa::Rope<T> {
   }
 }
 
-impl<T> Rope<T> for naive::Rope<T> {
+impl<T: Clone> Rope<T> for naive::Rope<T> {
   fn new() -> Self {
     Self::new()
   }